@@ -0,0 +1,218 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The storage primitives that `LigatureSled` needs from an underlying key-value store.
+//!
+//! `KvBackend` captures only what `lib.rs`, `query_tx.rs`, and `write_tx.rs` actually use
+//! (keyed get/contains, prefix scan, ordered range scan, named sub-tree open/drop, and a
+//! closure-based transaction per tree). `SledTree`/`SledBackend` are the only implementation
+//! today, but any other embedded store that can offer these primitives - LMDB, RocksDB,
+//! a plain SQLite table - can back `LigatureSled<B>` without touching the dataset or
+//! statement encoding logic in `ligature_kv`.
+
+use ligature::LigatureError;
+
+/// A single key/value pair as read back from a backend scan.
+pub type KvEntry = (Vec<u8>, Vec<u8>);
+
+/// A view into an in-flight transaction against one tree.
+///
+/// Implementations mirror sled's transactional semantics: `insert`/`remove` return the
+/// previous value (if any) so callers can tell a fresh insert from an overwrite, or a
+/// removal of a present key from a no-op.
+pub trait KvTransaction {
+    /// Read a value inside the transaction.
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, LigatureError>;
+    /// Insert a value, returning the previous value at `key`, if any.
+    fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<Option<Vec<u8>>, LigatureError>;
+    /// Remove a value, returning it if it was present.
+    fn remove(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>, LigatureError>;
+    /// Return `error` as an `Err`; returning that from the transaction closure aborts the
+    /// transaction and discards any writes made so far.
+    fn abort(&self, error: LigatureError) -> Result<(), LigatureError> {
+        Err(error)
+    }
+}
+
+/// A named sub-tree (sled calls these "trees") within a `KvBackend`.
+pub trait KvTree {
+    /// Read a value directly, outside of a transaction.
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, LigatureError>;
+    /// Scan all keys in this tree starting with `prefix`.
+    fn scan_prefix(
+        &self,
+        prefix: Vec<u8>,
+    ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>>;
+    /// Run `f` as a single atomic transaction against this tree.
+    fn transaction<T>(
+        &self,
+        f: impl Fn(&dyn KvTransaction) -> Result<T, LigatureError>,
+    ) -> Result<T, LigatureError>;
+}
+
+/// The primitives `LigatureSled` needs from an underlying key-value store.
+pub trait KvBackend: Send + Sync {
+    /// The named sub-tree type this backend hands back from `open_tree`.
+    type Tree: KvTree;
+
+    /// Check whether `key` is present at the top level of the store.
+    fn contains_key(&self, key: &[u8]) -> Result<bool, LigatureError>;
+    /// Insert `value` at `key` at the top level of the store.
+    fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), LigatureError>;
+    /// Remove `key` from the top level of the store.
+    fn remove(&self, key: &[u8]) -> Result<(), LigatureError>;
+    /// Scan all top-level keys starting with `prefix`.
+    fn scan_prefix(
+        &self,
+        prefix: Vec<u8>,
+    ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>>;
+    /// Scan all top-level keys in `from..to`, in key order.
+    fn range(
+        &self,
+        from: Vec<u8>,
+        to: Vec<u8>,
+    ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>>;
+    /// Open (creating if necessary) the named sub-tree for a dataset.
+    fn open_tree(&self, name: &str) -> Result<Self::Tree, LigatureError>;
+    /// Drop the named sub-tree and everything in it.
+    fn drop_tree(&self, name: &str) -> Result<(), LigatureError>;
+}
+
+mod sled_backend {
+    use super::{KvBackend, KvEntry, KvTransaction, KvTree};
+    use ligature::LigatureError;
+
+    /// The default `KvBackend`, backed by a `sled::Db`.
+    pub struct SledBackend(pub sled::Db);
+
+    impl KvBackend for SledBackend {
+        type Tree = SledTree;
+
+        fn contains_key(&self, key: &[u8]) -> Result<bool, LigatureError> {
+            self.0
+                .contains_key(key)
+                .map_err(|_| LigatureError("Error checking for key.".to_string()))
+        }
+
+        fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), LigatureError> {
+            self.0
+                .insert(key, value)
+                .map(|_| ())
+                .map_err(|_| LigatureError("Error inserting key.".to_string()))
+        }
+
+        fn remove(&self, key: &[u8]) -> Result<(), LigatureError> {
+            self.0
+                .remove(key)
+                .map(|_| ())
+                .map_err(|_| LigatureError("Error removing key.".to_string()))
+        }
+
+        fn scan_prefix(
+            &self,
+            prefix: Vec<u8>,
+        ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>> {
+            Box::new(self.0.scan_prefix(prefix).map(|res| {
+                res.map(|(k, v)| (k.to_vec(), v.to_vec()))
+                    .map_err(|_| LigatureError("Error scanning by prefix.".to_string()))
+            }))
+        }
+
+        fn range(
+            &self,
+            from: Vec<u8>,
+            to: Vec<u8>,
+        ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>> {
+            Box::new(self.0.range(from..to).map(|res| {
+                res.map(|(k, v)| (k.to_vec(), v.to_vec()))
+                    .map_err(|_| LigatureError("Error scanning by range.".to_string()))
+            }))
+        }
+
+        fn open_tree(&self, name: &str) -> Result<Self::Tree, LigatureError> {
+            self.0
+                .open_tree(name)
+                .map(SledTree)
+                .map_err(|_| LigatureError(format!("Error opening tree {}.", name)))
+        }
+
+        fn drop_tree(&self, name: &str) -> Result<(), LigatureError> {
+            self.0
+                .drop_tree(name)
+                .map(|_| ())
+                .map_err(|_| LigatureError(format!("Error dropping tree {}.", name)))
+        }
+    }
+
+    /// A sled-backed `KvTree`.
+    #[derive(Clone)]
+    pub struct SledTree(pub sled::Tree);
+
+    impl KvTree for SledTree {
+        fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, LigatureError> {
+            self.0
+                .get(key)
+                .map(|opt| opt.map(|v| v.to_vec()))
+                .map_err(|_| LigatureError("Error reading key.".to_string()))
+        }
+
+        fn scan_prefix(
+            &self,
+            prefix: Vec<u8>,
+        ) -> Box<dyn Iterator<Item = Result<KvEntry, LigatureError>>> {
+            Box::new(self.0.scan_prefix(prefix).map(|res| {
+                res.map(|(k, v)| (k.to_vec(), v.to_vec()))
+                    .map_err(|_| LigatureError("Error scanning tree by prefix.".to_string()))
+            }))
+        }
+
+        fn transaction<T>(
+            &self,
+            f: impl Fn(&dyn KvTransaction) -> Result<T, LigatureError>,
+        ) -> Result<T, LigatureError> {
+            self.0
+                .transaction(|transaction_tree| {
+                    let ctx = SledTransaction(transaction_tree.clone());
+                    match f(&ctx) {
+                        Ok(value) => Ok(value),
+                        Err(err) => sled::transaction::abort(err),
+                    }
+                })
+                .map_err(|e| LigatureError(format!("Error in transaction - {:?}.", e)))
+        }
+    }
+
+    /// Wraps a raw `sled::transaction::TransactionalTree` as a `KvTransaction`.
+    ///
+    /// Exposed at `pub(crate)` (rather than kept private) so that `LigatureSled`'s
+    /// sled-specific `batch_write`, which drives sled's multi-tree transaction directly,
+    /// can reuse the same `KvTransaction` adapter that single-tree writes go through.
+    pub(crate) struct SledTransaction(pub(crate) sled::transaction::TransactionalTree);
+
+    impl KvTransaction for SledTransaction {
+        fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, LigatureError> {
+            self.0
+                .get(key)
+                .map(|opt| opt.map(|v| v.to_vec()))
+                .map_err(|_| LigatureError("Error reading key in transaction.".to_string()))
+        }
+
+        fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<Option<Vec<u8>>, LigatureError> {
+            self.0
+                .insert(key, value)
+                .map(|opt| opt.map(|v| v.to_vec()))
+                .map_err(|_| LigatureError("Error inserting key in transaction.".to_string()))
+        }
+
+        fn remove(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>, LigatureError> {
+            self.0
+                .remove(key)
+                .map(|opt| opt.map(|v| v.to_vec()))
+                .map_err(|_| LigatureError("Error removing key in transaction.".to_string()))
+        }
+    }
+}
+
+pub use sled_backend::{SledBackend, SledTree};
+pub(crate) use sled_backend::SledTransaction;
@@ -4,108 +4,392 @@
 
 //#![deny(missing_docs)]
 
+mod kv_backend;
 mod query_tx;
 mod write_tx;
 
+use kv_backend::{KvBackend, KvTree, SledBackend};
 use ligature::{
     Attribute, Dataset, Ligature, LigatureError, PersistedStatement, QueryFn, QueryTx, Range,
     Statement, WriteFn, WriteTx,
 };
 use ligature_kv::{
-    chomp_assert, decode_dataset, encode_dataset, encode_dataset_match, prepend,
-    ATTRIBUTE_ID_COUNTER_KEY, DATASET_PREFIX, ENTITY_ID_COUNTER_KEY, STRING_LITERAL_ID_COUNTER_KEY,
+    chomp_assert, decode_dataset, decode_persisted_statement, encode_dataset, encode_dataset_match,
+    encode_statement, prepend, ATTRIBUTE_ID_COUNTER_KEY, DATASET_PREFIX, ENTITY_ID_COUNTER_KEY,
+    STRING_LITERAL_ID_COUNTER_KEY,
 };
 use query_tx::LigatureSledQueryTx;
-use std::sync::RwLock;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, RwLock};
 use write_tx::LigatureSledWriteTx;
 
-pub struct LigatureSled {
-    //TODO eventually I won't need this but for now to support ReadTx range searches I need this lock
-    //TODO an improvement on this would be pre-tree locks
-    store_lock: RwLock<sled::Db>,
+/// Reserved key in each dataset tree holding the maintained count of statements in that
+/// dataset, kept in sync by `LigatureSledWriteTx` so `statement_count` never has to scan.
+pub(crate) const STATEMENT_COUNT_KEY: u8 = 3;
+
+/// Reserved key in each dataset tree holding the maintained total encoded byte size of
+/// the statements in that dataset, kept in sync the same way as `STATEMENT_COUNT_KEY`.
+pub(crate) const DATASET_SIZE_KEY: u8 = 4;
+
+/// Reserved key in each dataset tree holding that dataset's configured `Quota`, if any.
+pub(crate) const DATASET_QUOTA_KEY: u8 = 5;
+
+/// An optional cap on how large a dataset may grow, set with `LigatureSled::set_dataset_quota`
+/// and enforced on every `write` to that dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quota {
+    /// The largest number of statements the dataset may hold, if capped.
+    pub max_statements: Option<u64>,
+    /// The largest total encoded byte size the dataset's statements may occupy, if capped.
+    pub max_bytes: Option<u64>,
+}
+
+impl Quota {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(18);
+        bytes.push(self.max_statements.is_some() as u8);
+        bytes.extend_from_slice(&self.max_statements.unwrap_or(0).to_be_bytes());
+        bytes.push(self.max_bytes.is_some() as u8);
+        bytes.extend_from_slice(&self.max_bytes.unwrap_or(0).to_be_bytes());
+        bytes
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, LigatureError> {
+        if bytes.len() != 18 {
+            return Err(LigatureError("Corrupt dataset quota.".to_string()));
+        }
+        let max_statements = (bytes[0] == 1)
+            .then(|| u64::from_be_bytes(bytes[1..9].try_into().unwrap()));
+        let max_bytes = (bytes[9] == 1)
+            .then(|| u64::from_be_bytes(bytes[10..18].try_into().unwrap()));
+        Ok(Quota {
+            max_statements,
+            max_bytes,
+        })
+    }
+}
+
+/// A `Ligature` store backed by a pluggable `KvBackend`, sled by default.
+///
+/// `LigatureSled` itself only knows how to encode/decode datasets and statements; every
+/// actual key-value operation goes through `B`, so a different embedded store (LMDB,
+/// RocksDB, ...) can back this type by implementing `KvBackend` without touching any of
+/// the logic here.
+///
+/// Locking is per-dataset: `catalog_lock` only guards the top-level listing of which
+/// datasets exist (`all_datasets`, `match_datasets_*`, `create_dataset`, `delete_dataset`),
+/// while `query`/`write` take the lock for just the one dataset tree they touch out of
+/// `dataset_locks`, so a write to one dataset never blocks a write to another.
+pub struct LigatureSled<B: KvBackend = SledBackend> {
+    store: B,
+    catalog_lock: RwLock<()>,
+    dataset_locks: RwLock<HashMap<String, Arc<RwLock<()>>>>,
 }
 
-impl LigatureSled {
+impl LigatureSled<SledBackend> {
     /// Create/Open an instance of LigatureSled at the given path.
     pub fn new(path: String) -> Result<Self, sled::Error> {
         let instance = sled::open(path)?;
-        Ok(Self {
-            store_lock: RwLock::new(instance),
-        })
+        Ok(Self::from_backend(SledBackend(instance)))
     }
 
     /// Create a temporary instance of LigatureSled that is deleted on close.
     /// Pass Some(String) if you want it located at a given path or None if you want the default from Sled.
     pub fn temp(path: Option<String>) -> Result<Self, sled::Error> {
-        match path {
-            None => {
-                let instance = sled::Config::default().temporary(true).open()?;
-                Ok(Self {
-                    store_lock: RwLock::new(instance),
-                })
-            }
-            Some(p) => {
-                let instance = sled::Config::default().temporary(true).path(p).open()?;
-                Ok(Self {
-                    store_lock: RwLock::new(instance),
-                })
-            }
-        }
+        let instance = match path {
+            None => sled::Config::default().temporary(true).open()?,
+            Some(p) => sled::Config::default().temporary(true).path(p).open()?,
+        };
+        Ok(Self::from_backend(SledBackend(instance)))
     }
 
     /// Create/Open an instance of LigatureSled with the given Sled config.
     /// Most people won't need this since the defaults are very good.
     pub fn from_config(config: sled::Config) -> Result<Self, sled::Error> {
         let instance = config.open()?;
-        Ok(Self {
-            store_lock: RwLock::new(instance),
-        })
+        Ok(Self::from_backend(SledBackend(instance)))
+    }
+
+    /// Atomically write to several datasets at once.
+    ///
+    /// Opens the tree for each of `datasets` and runs them under sled's multi-tree
+    /// `Transactional::transaction`, so either every write made through the per-dataset
+    /// `WriteTx` handles in `f` is committed, or none are. `f` is handed a map from
+    /// dataset name to that dataset's `WriteTx`, the same way a single `write` call is
+    /// handed one `WriteTx`.
+    ///
+    /// This is sled-specific - a multi-tree transaction isn't part of `KvBackend`, so a
+    /// future non-sled driver would need its own cross-dataset batch primitive.
+    pub fn batch_write<T>(
+        &self,
+        datasets: &[Dataset],
+        f: impl Fn(&std::collections::HashMap<String, LigatureSledWriteTx<'_>>) -> Result<T, LigatureError>,
+    ) -> Result<T, LigatureError> {
+        use sled::transaction::Transactional;
+
+        // Lock every involved dataset in a fixed (sorted) order, regardless of the order
+        // `datasets` was given in, so two overlapping `batch_write` calls can't deadlock
+        // on each other.
+        let mut names: Vec<String> = datasets.iter().map(|d| d.name().to_string()).collect();
+        names.sort();
+        names.dedup();
+        let locks: Vec<Arc<RwLock<()>>> = names.iter().map(|name| self.dataset_lock(name)).collect();
+        let _guards = locks
+            .iter()
+            .map(|lock| {
+                lock.write().map_err(|_| {
+                    LigatureError("Error starting batch write transaction.".to_string())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut trees = Vec::with_capacity(datasets.len());
+        for dataset in datasets {
+            let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
+            if !Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+                return Err(LigatureError(format!(
+                    "Error starting batch write - dataset {:?} doesn't exist.",
+                    dataset
+                )));
+            }
+            let tree = self.store.0.open_tree(dataset.name()).map_err(|_| {
+                LigatureError(format!("Error opening dataset tree for {:?}.", dataset))
+            })?;
+            trees.push((dataset.name().to_string(), tree));
+        }
+        let tree_refs: Vec<&sled::Tree> = trees.iter().map(|(_, tree)| tree).collect();
+        let res = tree_refs.as_slice().transaction(|transaction_trees| {
+            let contexts: Vec<kv_backend::SledTransaction> = transaction_trees
+                .iter()
+                .map(|transaction_tree| kv_backend::SledTransaction(transaction_tree.clone()))
+                .collect();
+            let write_txs: std::collections::HashMap<String, LigatureSledWriteTx<'_>> = trees
+                .iter()
+                .zip(contexts.iter())
+                .map(|((name, _), ctx)| (name.clone(), LigatureSledWriteTx::new(ctx)))
+                .collect();
+            let res = f(&write_txs);
+            if write_txs.values().all(|tx| tx.active.get()) {
+                match res {
+                    Ok(value) => Ok(value),
+                    Err(err) => sled::transaction::abort(err),
+                }
+            } else {
+                sled::transaction::abort(LigatureError("Aborting batch transaction.".to_string()))
+            }
+        });
+        res.map_err(|e| LigatureError(format!("Error with batch write - {:?}.", e)))
+    }
+}
+
+impl<B: KvBackend> LigatureSled<B> {
+    fn from_backend(store: B) -> Self {
+        Self {
+            store,
+            catalog_lock: RwLock::new(()),
+            dataset_locks: RwLock::new(HashMap::new()),
+        }
     }
 
     fn internal_dataset_exists(
-        store: &sled::Db,
-        encoded_dataset: &Vec<u8>,
+        store: &B,
+        encoded_dataset: &[u8],
     ) -> Result<bool, LigatureError> {
         store
-            .contains_key(&encoded_dataset)
+            .contains_key(encoded_dataset)
             .map_err(|_| LigatureError("Error checking for Dataset".to_string()))
     }
+
+    /// The per-dataset lock for `name`, creating it if this is the first time `name` has
+    /// been queried or written to.
+    fn dataset_lock(&self, name: &str) -> Arc<RwLock<()>> {
+        if let Some(lock) = self.dataset_locks.read().unwrap().get(name) {
+            return lock.clone();
+        }
+        self.dataset_locks
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(RwLock::new(())))
+            .clone()
+    }
+
+    /// The number of statements currently in `dataset`, read from the maintained counter
+    /// rather than by scanning the dataset tree.
+    pub fn statement_count(&self, dataset: &Dataset) -> Result<u64, LigatureError> {
+        let lock = self.dataset_lock(dataset.name());
+        let _guard = lock.read().map_err(|_| {
+            LigatureError("Error starting read transaction for statement_count.".to_string())
+        })?;
+        let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
+        if !Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            return Err(LigatureError(format!(
+                "Error reading statement count - dataset {:?} doesn't exist.",
+                dataset
+            )));
+        }
+        let tree = self
+            .store
+            .open_tree(dataset.name())
+            .map_err(|_| LigatureError("Error starting read transaction for statement_count.".to_string()))?;
+        let bytes = tree
+            .get(&[STATEMENT_COUNT_KEY])
+            .map_err(|_| LigatureError("Error reading statement count.".to_string()))?
+            .ok_or_else(|| LigatureError("Dataset is missing its statement count.".to_string()))?;
+        let bytes: [u8; 8] = bytes
+            .try_into()
+            .map_err(|_| LigatureError("Corrupt statement count.".to_string()))?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Cap how many statements and/or encoded bytes `dataset` may hold.
+    ///
+    /// Persisted in the dataset tree alongside the id counters and enforced by every
+    /// subsequent `write` to `dataset`; writes already committed before the quota was set
+    /// are not retroactively checked.
+    pub fn set_dataset_quota(&self, dataset: &Dataset, quota: Quota) -> Result<(), LigatureError> {
+        let lock = self.dataset_lock(dataset.name());
+        let _guard = lock.write().map_err(|_| {
+            LigatureError("Error starting write transaction to set dataset quota.".to_string())
+        })?;
+        let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
+        if !Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            return Err(LigatureError(format!(
+                "Error setting quota - dataset {:?} doesn't exist.",
+                dataset
+            )));
+        }
+        let tree = self.store.open_tree(dataset.name()).map_err(|_| {
+            LigatureError("Error starting write transaction to set dataset quota.".to_string())
+        })?;
+        tree.transaction(|tx| {
+            tx.insert(vec![DATASET_QUOTA_KEY], quota.encode())?;
+            Ok(())
+        })
+        .map_err(|_| LigatureError(format!("Error setting quota for dataset {:?}.", dataset)))
+    }
+
+    /// Serialize every dataset and its statements into `out` as a self-describing,
+    /// backend-independent stream of length-delimited records: per dataset, a name record,
+    /// a record holding its statement count as a big-endian `u64`, then for each statement
+    /// a key record and a value record as produced by `encode_statement`.
+    ///
+    /// Goes through `all_datasets`/`query` and the normal statement decoders rather than
+    /// copying raw tree bytes, so the resulting stream can be `import`ed into a store
+    /// backed by a different `KvBackend` than the one it was exported from.
+    pub fn export(&self, mut out: impl Write) -> Result<(), LigatureError> {
+        for dataset in self.all_datasets() {
+            let dataset = dataset?;
+            write_record(&mut out, dataset.name().as_bytes())?;
+            let statements = self.query(
+                &dataset,
+                Box::new(|tx| tx.all_statements().collect::<Result<Vec<_>, _>>()),
+            )?;
+            write_record(&mut out, &(statements.len() as u64).to_be_bytes())?;
+            for statement in statements {
+                let (key, value, _) = encode_statement(statement.statement)?;
+                write_record(&mut out, &key)?;
+                write_record(&mut out, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reload a stream produced by `export`, recreating each dataset with
+    /// `create_dataset` and replaying its statements through the normal `write` path.
+    pub fn import(&self, mut input: impl Read) -> Result<(), LigatureError> {
+        while let Some(name_bytes) = read_record(&mut input)? {
+            let name = String::from_utf8(name_bytes).map_err(|_| {
+                LigatureError("Error decoding dataset name during import.".to_string())
+            })?;
+            let dataset = Dataset::new(name).map_err(|_| {
+                LigatureError("Error constructing dataset during import.".to_string())
+            })?;
+            self.create_dataset(&dataset)?;
+
+            let count_bytes = read_record(&mut input)?
+                .ok_or_else(|| LigatureError("Truncated import stream.".to_string()))?;
+            let count_bytes: [u8; 8] = count_bytes
+                .try_into()
+                .map_err(|_| LigatureError("Corrupt statement count during import.".to_string()))?;
+            let count = u64::from_be_bytes(count_bytes);
+
+            let mut statements = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let key = read_record(&mut input)?
+                    .ok_or_else(|| LigatureError("Truncated import stream.".to_string()))?;
+                let value = read_record(&mut input)?
+                    .ok_or_else(|| LigatureError("Truncated import stream.".to_string()))?;
+                statements.push(decode_persisted_statement(key, value)?);
+            }
+            self.write(
+                &dataset,
+                Box::new(move |tx| {
+                    for statement in statements {
+                        tx.add_statement(statement.statement)?;
+                    }
+                    Ok(())
+                }),
+            )?;
+        }
+        Ok(())
+    }
 }
 
-impl Ligature for LigatureSled {
+fn write_record(out: &mut impl Write, bytes: &[u8]) -> Result<(), LigatureError> {
+    out.write_all(&(bytes.len() as u64).to_be_bytes())
+        .and_then(|_| out.write_all(bytes))
+        .map_err(|_| LigatureError("Error writing export record.".to_string()))
+}
+
+fn read_record(input: &mut impl Read) -> Result<Option<Vec<u8>>, LigatureError> {
+    let mut len_bytes = [0u8; 8];
+    match input.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(_) => return Err(LigatureError("Error reading export record length.".to_string())),
+    }
+    let len = u64::from_be_bytes(len_bytes) as usize;
+    let mut bytes = vec![0u8; len];
+    input
+        .read_exact(&mut bytes)
+        .map_err(|_| LigatureError("Error reading export record.".to_string()))?;
+    Ok(Some(bytes))
+}
+
+impl<B: KvBackend> Ligature for LigatureSled<B> {
     fn all_datasets(&self) -> Box<dyn Iterator<Item = Result<Dataset, LigatureError>>> {
-        let store = self.store_lock.read().unwrap(); //to use map_err
-        let iter = store.scan_prefix(vec![DATASET_PREFIX]); //store.iter();
+        let _guard = self.catalog_lock.read().unwrap(); //to use map_err
+        let iter = self.store.scan_prefix(vec![DATASET_PREFIX]);
         Box::new(iter.map(|ds| match ds {
-            Ok(dataset) => decode_dataset(chomp_assert(DATASET_PREFIX, dataset.0.to_vec())?),
+            Ok((key, _)) => decode_dataset(chomp_assert(DATASET_PREFIX, key)?),
             Err(_) => Err(LigatureError("Error iterating Datasets.".to_string())),
         }))
     }
 
     fn dataset_exists(&self, dataset: &Dataset) -> Result<bool, LigatureError> {
-        let store = self.store_lock.read().unwrap(); //to use map_err
+        let _guard = self.catalog_lock.read().unwrap(); //to use map_err
         let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(&dataset));
-        LigatureSled::internal_dataset_exists(&store, &encoded_dataset)
+        Self::internal_dataset_exists(&self.store, &encoded_dataset)
     }
 
     fn match_datasets_prefix(
         &self,
         prefix: &str,
     ) -> Box<dyn Iterator<Item = Result<Dataset, LigatureError>>> {
-        let store_res = self.store_lock.read().map_err(|_| {
+        let guard_res = self.catalog_lock.read().map_err(|_| {
             LigatureError(
                 "Error starting read transaction when matching dataset prefixes.".to_string(),
             )
         });
-        match store_res {
-            Ok(store) => {
+        match guard_res {
+            Ok(_guard) => {
                 let encoded_prefix = prepend(DATASET_PREFIX, encode_dataset_match(prefix));
-                let res = store.scan_prefix(encoded_prefix);
+                let res = self.store.scan_prefix(encoded_prefix);
                 Box::new(res.map(|value_res| match value_res {
-                    Ok(value) => decode_dataset(chomp_assert(DATASET_PREFIX, value.0.to_vec())?),
-                    Err(e) => Err(LigatureError(
-                        "Error presfix matching Datasets.".to_string(),
-                    )),
+                    Ok((key, _)) => decode_dataset(chomp_assert(DATASET_PREFIX, key)?),
+                    Err(e) => Err(e),
                 }))
             }
             Err(e) => Box::new(std::iter::once(Err(e))),
@@ -117,21 +401,19 @@ impl Ligature for LigatureSled {
         from: &str,
         to: &str,
     ) -> Box<dyn Iterator<Item = Result<Dataset, LigatureError>>> {
-        let store_res = self.store_lock.read().map_err(|_| {
+        let guard_res = self.catalog_lock.read().map_err(|_| {
             LigatureError(
                 "Error starting read transaction when matching dataset ranges.".to_string(),
             )
         });
-        match store_res {
-            Ok(store) => {
+        match guard_res {
+            Ok(_guard) => {
                 let encoded_from = prepend(DATASET_PREFIX, encode_dataset_match(from));
                 let encoded_to = prepend(DATASET_PREFIX, encode_dataset_match(to));
-                let res = store.range(encoded_from..encoded_to);
+                let res = self.store.range(encoded_from, encoded_to);
                 Box::new(res.map(|value_res| match value_res {
-                    Ok(value) => decode_dataset(chomp_assert(DATASET_PREFIX, value.0.to_vec())?),
-                    Err(e) => Err(LigatureError(
-                        "Error presfix matching Datasets.".to_string(),
-                    )),
+                    Ok((key, _)) => decode_dataset(chomp_assert(DATASET_PREFIX, key)?),
+                    Err(e) => Err(e),
                 }))
             }
             Err(e) => Box::new(std::iter::once(Err(e))),
@@ -139,48 +421,49 @@ impl Ligature for LigatureSled {
     }
 
     fn create_dataset(&self, dataset: &Dataset) -> Result<(), LigatureError> {
-        let store = self.store_lock.write().map_err(|_| {
+        let dataset_lock = self.dataset_lock(dataset.name());
+        let _dataset_guard = dataset_lock.write().map_err(|_| {
+            LigatureError(format!(
+                "Error starting write transaction when adding dataset {:?}.",
+                dataset
+            ))
+        })?;
+        let _catalog_guard = self.catalog_lock.write().map_err(|_| {
             LigatureError(format!(
                 "Error starting write transaction when adding dataset {:?}.",
                 dataset
             ))
         })?;
         let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
-        if !LigatureSled::internal_dataset_exists(&store, &encoded_dataset)? {
-            store
+        if !Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            self.store
                 .insert(encoded_dataset, vec![])
                 .map_err(|_| LigatureError(format!("Error inserting dataset {:?}.", dataset)))?;
-            let dataset_tree = store.open_tree(dataset.name()).map_err(|_| {
+            let dataset_tree = self.store.open_tree(dataset.name()).map_err(|_| {
                 LigatureError(format!("Error creating dataset tree for {:?}.", dataset))
             })?;
             let id_start: u64 = 0;
             dataset_tree
-                .insert(vec![ENTITY_ID_COUNTER_KEY], id_start.to_be_bytes().to_vec())
-                .map_err(|_| {
-                    LigatureError(format!(
-                        "Error creating dataset entity id counter for {:?}.",
-                        dataset
-                    ))
-                })?;
-            dataset_tree
-                .insert(
-                    vec![ATTRIBUTE_ID_COUNTER_KEY],
-                    id_start.to_be_bytes().to_vec(),
-                )
-                .map_err(|_| {
-                    LigatureError(format!(
-                        "Error creating dataset attribute id counter for {:?}.",
-                        dataset
-                    ))
-                })?;
-            dataset_tree
-                .insert(
-                    vec![STRING_LITERAL_ID_COUNTER_KEY],
-                    id_start.to_be_bytes().to_vec(),
-                )
+                .transaction(|tx| {
+                    tx.insert(vec![ENTITY_ID_COUNTER_KEY], id_start.to_be_bytes().to_vec())?;
+                    tx.insert(
+                        vec![ATTRIBUTE_ID_COUNTER_KEY],
+                        id_start.to_be_bytes().to_vec(),
+                    )?;
+                    tx.insert(
+                        vec![STRING_LITERAL_ID_COUNTER_KEY],
+                        id_start.to_be_bytes().to_vec(),
+                    )?;
+                    tx.insert(
+                        vec![STATEMENT_COUNT_KEY],
+                        id_start.to_be_bytes().to_vec(),
+                    )?;
+                    tx.insert(vec![DATASET_SIZE_KEY], id_start.to_be_bytes().to_vec())?;
+                    Ok(())
+                })
                 .map_err(|_| {
                     LigatureError(format!(
-                        "Error creating dataset string literal id counter for {:?}.",
+                        "Error creating dataset id counters for {:?}.",
                         dataset
                     ))
                 })?;
@@ -189,15 +472,19 @@ impl Ligature for LigatureSled {
     }
 
     fn delete_dataset(&self, dataset: &Dataset) -> Result<(), LigatureError> {
-        let store = self.store_lock.write().map_err(|_| {
+        let dataset_lock = self.dataset_lock(dataset.name());
+        let _dataset_guard = dataset_lock.write().map_err(|_| {
+            LigatureError("Error starting write transaction when deleting dataset.".to_string())
+        })?;
+        let _catalog_guard = self.catalog_lock.write().map_err(|_| {
             LigatureError("Error starting write transaction when deleting dataset.".to_string())
         })?;
         let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
-        if LigatureSled::internal_dataset_exists(&store, &encoded_dataset)? {
-            store
+        if Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            self.store
                 .remove(&encoded_dataset)
                 .map_err(|_| LigatureError("Error removing dataset.".to_string()))?;
-            store
+            self.store
                 .drop_tree(dataset.name())
                 .map_err(|_| LigatureError("Error dropping dataset tree.".to_string()))?;
         }
@@ -205,13 +492,14 @@ impl Ligature for LigatureSled {
     }
 
     fn query<T>(&self, dataset: &Dataset, f: QueryFn<T>) -> Result<T, LigatureError> {
-        let store = self
-            .store_lock
+        let dataset_lock = self.dataset_lock(dataset.name());
+        let _guard = dataset_lock
             .read()
             .map_err(|_| LigatureError("Error starting query transaction.".to_string()))?;
         let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
-        if LigatureSled::internal_dataset_exists(&store, &encoded_dataset)? {
-            let tree = store
+        if Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            let tree = self
+                .store
                 .open_tree(dataset.name())
                 .map_err(|_| LigatureError("Error starting query transaction.".to_string()))?;
             f(Box::new(&LigatureSledQueryTx::new(tree)))
@@ -223,28 +511,25 @@ impl Ligature for LigatureSled {
     }
 
     fn write<T>(&self, dataset: &Dataset, f: WriteFn<T>) -> Result<T, LigatureError> {
-        let store = self
-            .store_lock
+        let dataset_lock = self.dataset_lock(dataset.name());
+        let _guard = dataset_lock
             .write()
             .map_err(|_| LigatureError("Error starting write transaction.".to_string()))?;
         let encoded_dataset = prepend(DATASET_PREFIX, encode_dataset(dataset));
-        if LigatureSled::internal_dataset_exists(&store, &encoded_dataset)? {
-            let tree = store
+        if Self::internal_dataset_exists(&self.store, &encoded_dataset)? {
+            let tree = self
+                .store
                 .open_tree(dataset.name())
                 .map_err(|_| LigatureError("Error starting write transaction.".to_string()))?;
-            let res = tree.transaction(|transaction_tree| {
-                let write_tx = LigatureSledWriteTx::new(transaction_tree.clone());
+            tree.transaction(|transaction| {
+                let write_tx = LigatureSledWriteTx::new(transaction);
                 let res = f(Box::new(&write_tx));
                 if write_tx.active.get() {
-                    match res {
-                        Ok(value) => Ok(value),
-                        Err(err) => sled::transaction::abort(err),
-                    }
+                    res
                 } else {
-                    sled::transaction::abort(LigatureError("Aborting transaction.".to_string()))
+                    Err(LigatureError("Aborting transaction.".to_string()))
                 }
-            });
-            res.map_err(|e| LigatureError(format!("Error with writetx - {:?}.", e)))
+            })
         } else {
             Err(LigatureError(
                 "Error starting write transaction.".to_string(),
@@ -0,0 +1,54 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::kv_backend::KvTree;
+use ligature::{Attribute, LigatureError, PersistedStatement, QueryTx, Value};
+use ligature_kv::{
+    chomp_assert, decode_persisted_statement, encode_statement_match, prepend, STATEMENT_PREFIX,
+};
+
+/// A `QueryTx` that reads directly from a single `KvTree`, which it holds by value.
+pub struct LigatureSledQueryTx<T: KvTree> {
+    tree: T,
+}
+
+impl<T: KvTree> LigatureSledQueryTx<T> {
+    pub fn new(tree: T) -> Self {
+        Self { tree }
+    }
+}
+
+impl<T: KvTree> QueryTx for LigatureSledQueryTx<T> {
+    fn all_statements(
+        &self,
+    ) -> Box<dyn Iterator<Item = Result<PersistedStatement, LigatureError>>> {
+        let prefix = vec![STATEMENT_PREFIX];
+        Box::new(
+            self.tree
+                .scan_prefix(prefix)
+                .map(|entry| match entry {
+                    Ok((key, value)) => {
+                        decode_persisted_statement(chomp_assert(STATEMENT_PREFIX, key)?, value)
+                    }
+                    Err(e) => Err(e),
+                }),
+        )
+    }
+
+    fn match_statements(
+        &self,
+        entity: Option<&str>,
+        attribute: Option<&Attribute>,
+        value: Option<&Value>,
+    ) -> Box<dyn Iterator<Item = Result<PersistedStatement, LigatureError>>> {
+        let matcher = encode_statement_match(entity, attribute, value);
+        let prefix = prepend(STATEMENT_PREFIX, matcher);
+        Box::new(self.tree.scan_prefix(prefix).map(|entry| match entry {
+            Ok((key, value)) => {
+                decode_persisted_statement(chomp_assert(STATEMENT_PREFIX, key)?, value)
+            }
+            Err(e) => Err(e),
+        }))
+    }
+}
@@ -0,0 +1,110 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::kv_backend::KvTransaction;
+use crate::{Quota, DATASET_QUOTA_KEY, DATASET_SIZE_KEY, STATEMENT_COUNT_KEY};
+use ligature::{LigatureError, PersistedStatement, Statement, WriteTx};
+use ligature_kv::{encode_statement, STATEMENT_PREFIX};
+use std::cell::Cell;
+
+/// A `WriteTx` backed by a single `KvTransaction`, shared for the lifetime of one
+/// transaction closure (`KvTree::transaction`, or sled's multi-tree transaction in
+/// `batch_write`).
+pub struct LigatureSledWriteTx<'a> {
+    transaction: &'a dyn KvTransaction,
+    /// Cleared by `cancel`; checked by the caller after `f` returns to decide whether to
+    /// commit or abort the underlying transaction.
+    pub active: Cell<bool>,
+}
+
+impl<'a> LigatureSledWriteTx<'a> {
+    pub fn new(transaction: &'a dyn KvTransaction) -> Self {
+        Self {
+            transaction,
+            active: Cell::new(true),
+        }
+    }
+}
+
+impl<'a> LigatureSledWriteTx<'a> {
+    fn read_counter(&self, key: u8) -> Result<u64, LigatureError> {
+        match self.transaction.get(&[key])? {
+            Some(bytes) => {
+                let bytes: [u8; 8] = bytes
+                    .try_into()
+                    .map_err(|_| LigatureError("Corrupt dataset counter.".to_string()))?;
+                Ok(u64::from_be_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Add `delta` to one of the dataset's maintained `u64` counters (statement count or
+    /// total encoded size), inside the same transaction as the insert/remove that
+    /// triggered it, so the counter can never drift from the actual data even if the
+    /// write later aborts. Returns the counter's new value.
+    fn adjust_counter(&self, key: u8, delta: i64) -> Result<u64, LigatureError> {
+        let current = self.read_counter(key)?;
+        let updated = (current as i64 + delta) as u64;
+        self.transaction
+            .insert(vec![key], updated.to_be_bytes().to_vec())?;
+        Ok(updated)
+    }
+
+    /// Abort the write if it pushed the dataset past its configured `Quota`, if any.
+    fn enforce_quota(&self, statement_count: u64, dataset_size: u64) -> Result<(), LigatureError> {
+        let quota = match self.transaction.get(&[DATASET_QUOTA_KEY])? {
+            Some(bytes) => Quota::decode(&bytes)?,
+            None => return Ok(()),
+        };
+        if let Some(max_statements) = quota.max_statements {
+            if statement_count > max_statements {
+                return Err(LigatureError(format!(
+                    "Dataset statement quota exceeded: {} > {}.",
+                    statement_count, max_statements
+                )));
+            }
+        }
+        if let Some(max_bytes) = quota.max_bytes {
+            if dataset_size > max_bytes {
+                return Err(LigatureError(format!(
+                    "Dataset byte size quota exceeded: {} > {}.",
+                    dataset_size, max_bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> WriteTx for LigatureSledWriteTx<'a> {
+    fn add_statement(&self, statement: Statement) -> Result<PersistedStatement, LigatureError> {
+        let (key, value, persisted) = encode_statement(statement)?;
+        let full_key = [&[STATEMENT_PREFIX], key.as_slice()].concat();
+        let entry_size = (full_key.len() + value.len()) as i64;
+        let previous = self.transaction.insert(full_key, value)?;
+        if previous.is_none() {
+            let statement_count = self.adjust_counter(STATEMENT_COUNT_KEY, 1)?;
+            let dataset_size = self.adjust_counter(DATASET_SIZE_KEY, entry_size)?;
+            self.enforce_quota(statement_count, dataset_size)?;
+        }
+        Ok(persisted)
+    }
+
+    fn remove_statement(&self, statement: PersistedStatement) -> Result<(), LigatureError> {
+        let (key, value, _) = encode_statement(statement.statement)?;
+        let full_key = [&[STATEMENT_PREFIX], key.as_slice()].concat();
+        let entry_size = (full_key.len() + value.len()) as i64;
+        let previous = self.transaction.remove(full_key)?;
+        if previous.is_some() {
+            self.adjust_counter(STATEMENT_COUNT_KEY, -1)?;
+            self.adjust_counter(DATASET_SIZE_KEY, -entry_size)?;
+        }
+        Ok(())
+    }
+
+    fn cancel(&self) {
+        self.active.set(false);
+    }
+}